@@ -2,14 +2,16 @@
 #![feature(exact_size_is_empty)]
 
 use itertools::{iproduct, Itertools};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Display;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 enum Round {
     One,
     Two,
@@ -28,7 +30,7 @@ impl Round {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 enum Rank {
     Three,
     Four,
@@ -45,7 +47,7 @@ enum Rank {
     Two,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter, PartialOrd, Ord, Serialize, Deserialize)]
 enum Suit {
     Diamond,
     Club,
@@ -97,6 +99,11 @@ impl Suit {
             Self::Spade | Self::Club => Color::Black,
         }
     }
+
+    fn from_index(index: u8) -> Self {
+        const SUITS: [Suit; 4] = [Suit::Diamond, Suit::Club, Suit::Heart, Suit::Spade];
+        SUITS[index as usize]
+    }
 }
 
 impl Rank {
@@ -108,69 +115,137 @@ impl Rank {
             Self::Eight | Self::Nine | Self::Ten | Self::Jack | Self::Queen | Self::King => 10,
         }
     }
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
-enum Card {
-    Regular(Rank, Suit),
-    Joker,
-}
 
-impl Display for Card {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Card::Regular(r, s) => f.write_fmt(format_args!("{}{}", r, s)),
-            Card::Joker => f.write_str("🃏"),
-        }
+    fn from_index(index: u8) -> Self {
+        const RANKS: [Rank; 13] = [
+            Rank::Three,
+            Rank::Four,
+            Rank::Five,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Eight,
+            Rank::Nine,
+            Rank::Ten,
+            Rank::Jack,
+            Rank::Queen,
+            Rank::King,
+            Rank::Ace,
+            Rank::Two,
+        ];
+        RANKS[index as usize]
     }
 }
 
+/// A single card packed into one byte: `rank * 4 + suit` for regular cards,
+/// or the sentinel `JOKER` (`13 * 4`, one past the last regular card) for
+/// either of the two jokers. This keeps the hands, decks and melds that get
+/// copied around all game long (`Deck`, `Vec<Card>`, `HashMap<Rank,
+/// Vec<Card>>`) to a single byte per card instead of a two-field enum.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord, Serialize, Deserialize)]
+struct Card(u8);
+
+const JOKER: u8 = 13 * 4;
+
 impl Card {
+    pub fn regular(rank: Rank, suit: Suit) -> Self {
+        Card(rank as u8 * 4 + suit as u8)
+    }
+
+    pub fn joker() -> Self {
+        Card(JOKER)
+    }
+
+    pub fn rank(self) -> Option<Rank> {
+        (self.0 < JOKER).then(|| Rank::from_index(self.0 >> 2))
+    }
+
+    pub fn suit(self) -> Option<Suit> {
+        (self.0 < JOKER).then(|| Suit::from_index(self.0 & 3))
+    }
+
     pub fn iter() -> impl Iterator<Item = Self> {
         iproduct!(Rank::iter(), Suit::iter())
-            .map(|(rank, suit)| Card::Regular(rank, suit))
-            .chain([Card::Joker, Card::Joker])
+            .map(|(rank, suit)| Card::regular(rank, suit))
+            .chain([Card::joker(), Card::joker()])
     }
 
     pub fn points(self) -> usize {
-        match self {
-            Card::Regular(Rank::Three, s) if s.color() == Color::Red => 100,
-            Card::Regular(rank, _) => rank.points(),
-            Card::Joker => 50,
+        match self.rank() {
+            Some(Rank::Three) if self.suit().unwrap().color() == Color::Red => 100,
+            Some(rank) => rank.points(),
+            None => 50,
         }
     }
 
     pub fn is_wild(self) -> bool {
-        match self {
-            Card::Joker | Card::Regular(Rank::Two, _) => true,
-            _ => false,
-        }
+        matches!(self.rank(), None | Some(Rank::Two))
     }
 
     pub fn can_be_booked(self) -> bool {
-        match self {
-            Self::Regular(Rank::Three | Rank::Two, _) | Self::Joker => false,
-            _ => true,
-        }
+        !matches!(self.rank(), None | Some(Rank::Three) | Some(Rank::Two))
     }
+}
 
-    pub fn rank(self) -> Option<Rank> {
-        match self {
-            Card::Regular(r, _) => Some(r),
-            Card::Joker => None,
+impl Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.rank(), self.suit()) {
+            (Some(r), Some(s)) => f.write_fmt(format_args!("{}{}", r, s)),
+            _ => f.write_str("🃏"),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct PlayerCards {
     hand: Vec<Card>,
     foot: Option<Vec<Card>>,
     books: Vec<Vec<Card>>,
     red_threes: usize,
+    #[serde(with = "rank_map_serde")]
     play_area: HashMap<Rank, Vec<Card>>,
 }
 
+/// Serializes a `HashMap<Rank, Vec<Card>>` as a list of `{rank, cards}`
+/// entries sorted by rank, so snapshots round-trip byte-for-byte instead of
+/// depending on the hash map's iteration order.
+mod rank_map_serde {
+    use super::{Card, Rank};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry {
+        rank: Rank,
+        cards: Vec<Card>,
+    }
+
+    pub fn serialize<S>(map: &HashMap<Rank, Vec<Card>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entries: Vec<Entry> = map
+            .iter()
+            .map(|(rank, cards)| Entry {
+                rank: *rank,
+                cards: cards.clone(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.rank);
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashMap<Rank, Vec<Card>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<Entry>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.rank, entry.cards))
+            .collect())
+    }
+}
+
 impl PlayerCards {
     pub fn can_play(
         &self,
@@ -212,13 +287,10 @@ impl PlayerCards {
     }
 
     pub fn can_play_rank(&self, rank: Rank, cards: &[Card]) -> Result<(), TurnError> {
-        if !cards.iter().all(|c| {
-            c.is_wild()
-                || match c {
-                    Card::Regular(r, _) => *r == rank,
-                    _ => unreachable!("We filtered above"),
-                }
-        }) {
+        if !cards
+            .iter()
+            .all(|c| c.is_wild() || c.rank() == Some(rank))
+        {
             return Err(TurnError::NotAllCardsMatchRank);
         }
 
@@ -278,11 +350,35 @@ impl PlayerCards {
         self.clean_books() >= 1 && self.dirty_books() >= 1
     }
 
+    pub fn score(&self) -> isize {
+        let base: isize = self.clean_books() as isize * 500
+            + self.dirty_books() as isize * 300
+            + self.seven_books() as isize * 1500;
+
+        let count: isize = self
+            .books
+            .iter()
+            .flat_map(|x| x.iter())
+            .chain(self.play_area.iter().flat_map(|(_, cs)| cs.iter()))
+            .map(|x| x.points() as isize)
+            .sum();
+
+        let hand_points: isize = self
+            .hand
+            .iter()
+            .chain(self.foot.iter().flatten())
+            .map(|c| c.points() as isize)
+            .sum();
+
+        (self.red_threes as isize) * 100 + base + count - hand_points
+    }
+
     pub fn play_rank(&mut self, rank: Rank, cards: &[Card]) -> Result<(), TurnError> {
         self.can_play_rank(rank, cards)?;
-        // todo: need to detect if we would be able to go out after playing (not currently implemented)
-        // also need to actually undo the play if we fail with can't go out. this check should
-        // really be in the can_play_rank function
+        // todo: play_rank itself still doesn't check up front whether playing these
+        // cards leaves us able to go out, nor does it roll back a play that turns out
+        // to be a dead end; see `PlayerCards::best_play` for a solver that answers
+        // "can I go out?" before committing to a set of plays.
         let can_go_out = self.can_go_out();
         let already_played = self.play_area.entry(rank).or_insert(vec![]);
 
@@ -312,9 +408,148 @@ impl PlayerCards {
 
         Ok(())
     }
+
+    /// Searches for the legal way to extend `play_area` from `hand` that
+    /// maximizes the resulting score, backtracking rank by rank over how
+    /// many naturals and how many of the limited pool of wilds to commit
+    /// to each one. Reports whether the result leaves us able to go out
+    /// (>=1 clean book, >=1 dirty book) -- the check `play_rank`'s TODO
+    /// says isn't implemented.
+    pub fn best_play(&self, round: Round) -> Option<PlannedTurn> {
+        let ctx = SearchContext {
+            round,
+            has_melded: self.play_area.values().flatten().any(|_| true),
+            ranks: self
+                .hand
+                .iter()
+                .filter(|c| c.can_be_booked())
+                .filter_map(|c| c.rank())
+                .unique()
+                .collect(),
+        };
+
+        // Wilds are otherwise interchangeable for legality, so always
+        // committing from the high-value end of the pool first is never
+        // worse than any other allocation of the same count of wilds.
+        let mut wild_cards: Vec<Card> = self.hand.iter().copied().filter(|c| c.is_wild()).collect();
+        wild_cards.sort_by_key(|card| std::cmp::Reverse(card.points()));
+
+        let mut best: Option<(isize, HashMap<Rank, Vec<Card>>)> = None;
+        self.search(&ctx, 0, &wild_cards, HashMap::new(), &mut best);
+
+        let (_, plays) = best?;
+        let mut after = self.clone();
+        after.play(round, &plays).expect("search only keeps legal plays");
+
+        Some(PlannedTurn {
+            plays,
+            can_go_out: after.can_go_out(),
+        })
+    }
+
+    /// Branch-and-bound core of `best_play`: `ctx.ranks[idx..]` are the
+    /// ranks still undecided, `wilds_pool` is whatever wilds haven't been
+    /// committed to an earlier rank yet (highest-value first), and
+    /// `current` is the partial assignment built up so far.
+    fn search(
+        &self,
+        ctx: &SearchContext,
+        idx: usize,
+        wilds_pool: &[Card],
+        current: HashMap<Rank, Vec<Card>>,
+        best: &mut Option<(isize, HashMap<Rank, Vec<Card>>)>,
+    ) {
+        if idx == ctx.ranks.len() {
+            if current.is_empty() {
+                return;
+            }
+
+            let meld_points: usize = current.values().flatten().map(|c| c.points()).sum();
+            if !ctx.has_melded && meld_points < ctx.round.meld() {
+                return;
+            }
+
+            let mut after = self.clone();
+            if after.play(ctx.round, &current).is_err() {
+                return;
+            }
+
+            let score = after.score();
+            if best.as_ref().map_or(true, |(best_score, _)| score > *best_score) {
+                *best = Some((score, current));
+            }
+            return;
+        }
+
+        // Bound: give up on this branch if even committing every remaining
+        // natural and wild can't reach the initial meld.
+        if !ctx.has_melded {
+            let reachable: usize = current
+                .values()
+                .flatten()
+                .chain(wilds_pool.iter())
+                .chain(self.hand.iter().filter(|c| {
+                    c.rank().map_or(false, |r| ctx.ranks[idx..].contains(&r))
+                }))
+                .map(|c| c.points())
+                .sum();
+            if reachable < ctx.round.meld() {
+                return;
+            }
+        }
+
+        let rank = ctx.ranks[idx];
+        let naturals: Vec<Card> = self
+            .hand
+            .iter()
+            .copied()
+            .filter(|c| c.rank() == Some(rank))
+            .collect();
+
+        // Leave this rank untouched this turn.
+        self.search(ctx, idx + 1, wilds_pool, current.clone(), best);
+
+        for num_naturals in 0..=naturals.len() {
+            for num_wilds in 0..=wilds_pool.len() {
+                if num_naturals == 0 && num_wilds == 0 {
+                    continue;
+                }
+
+                let mut cards = naturals[..num_naturals].to_vec();
+                cards.extend_from_slice(&wilds_pool[..num_wilds]);
+
+                if self.can_play_rank(rank, &cards).is_err() {
+                    continue;
+                }
+
+                let mut next = current.clone();
+                next.insert(rank, cards);
+
+                self.search(ctx, idx + 1, &wilds_pool[num_wilds..], next, best);
+            }
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+/// Bundles the parts of `PlayerCards::search`'s state that stay constant
+/// across the whole backtrack, so the recursive call doesn't have to pass
+/// them one by one (and trip clippy's too-many-arguments lint).
+struct SearchContext {
+    round: Round,
+    has_melded: bool,
+    ranks: Vec<Rank>,
+}
+
+/// The outcome of `PlayerCards::best_play`: the highest-scoring legal set
+/// of plays found, and whether committing to it leaves the player able to
+/// go out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PlannedTurn {
+    plays: HashMap<Rank, Vec<Card>>,
+    can_go_out: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Deck(Vec<Card>);
 
 impl Deck {
@@ -323,12 +558,27 @@ impl Deck {
     }
 
     pub fn deal(num_players: usize) -> Self {
+        Self::deal_with_rng(num_players, &mut thread_rng())
+    }
+
+    /// Like [`Deck::deal`], but shuffled with a seeded RNG so the exact
+    /// same deck order can be reproduced later from the same seed -- handy
+    /// for debugging a reported bug or writing deterministic tests.
+    // todo: there's no discard-pile-into-deck recycling shuffle yet for this
+    // rng to be threaded through (the deck just runs out, see
+    // `TurnError::NotEnoughCards`), so this only makes the initial deal
+    // reproducible.
+    pub fn deal_seeded(num_players: usize, seed: u64) -> Self {
+        Self::deal_with_rng(num_players, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn deal_with_rng(num_players: usize, rng: &mut impl Rng) -> Self {
         let mut deck: Vec<_> = (0..(num_players + 1))
             .into_iter()
             .flat_map(|_| Card::iter())
             .collect();
 
-        deck.shuffle(&mut thread_rng());
+        deck.shuffle(rng);
 
         Self(deck)
     }
@@ -355,9 +605,9 @@ impl Deck {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, EnumIter)]
+#[derive(Clone, PartialEq, Eq, Debug, EnumIter, Serialize, Deserialize)]
 enum DrawAction {
-    Pickup(HashMap<Rank, Vec<Card>>),
+    Pickup(#[serde(with = "rank_map_serde")] HashMap<Rank, Vec<Card>>),
     Draw,
 }
 
@@ -381,7 +631,7 @@ enum TurnError {
     DeckIsLockedNeedTwoInHand,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Game {
     players: Vec<PlayerCards>,
     round: Round,
@@ -404,8 +654,16 @@ macro_rules! undo_on_error {
 
 impl Game {
     pub fn deal(round: Round, num_players: usize) -> Self {
-        let mut deck = Deck::deal(num_players);
+        Self::deal_from(round, num_players, Deck::deal(num_players))
+    }
 
+    /// Like [`Game::deal`], but deals from a [`Deck::deal_seeded`] so the
+    /// whole round can be reproduced exactly from the same seed.
+    pub fn deal_seeded(round: Round, num_players: usize, seed: u64) -> Self {
+        Self::deal_from(round, num_players, Deck::deal_seeded(num_players, seed))
+    }
+
+    fn deal_from(round: Round, num_players: usize, mut deck: Deck) -> Self {
         let players = (0..num_players)
             .into_iter()
             .map(|_| PlayerCards {
@@ -426,32 +684,19 @@ impl Game {
         }
     }
 
-    pub fn score(&self) -> Vec<isize> {
-        self.players
-            .iter()
-            .map(|player| {
-                let base: isize = player.clean_books() as isize * 500
-                    + player.dirty_books() as isize * 300
-                    + player.seven_books() as isize * 1500;
-
-                let count: isize = player
-                    .books
-                    .iter()
-                    .flat_map(|x| x.iter())
-                    .chain(player.play_area.iter().flat_map(|(_, cs)| cs.iter()))
-                    .map(|x| x.points() as isize)
-                    .sum();
+    /// Snapshots the entire table state as compact, stable JSON, suitable
+    /// for persisting between turns or sending over the network.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Game contains no non-serializable state")
+    }
 
-                let hand_points: isize = player
-                    .hand
-                    .iter()
-                    .chain(player.foot.iter().flatten())
-                    .map(|c| c.points() as isize)
-                    .sum();
+    /// Restores a `Game` previously produced by [`Game::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
 
-                (player.red_threes as isize) * 100 + base + count - hand_points
-            })
-            .collect()
+    pub fn score(&self) -> Vec<isize> {
+        self.players.iter().map(PlayerCards::score).collect()
     }
 
     fn resolve_red_threes(&mut self, player_idx: usize) -> Result<(), TurnError> {
@@ -459,9 +704,9 @@ impl Game {
 
         let red_threes_in_hand = player
             .hand
-            .drain_filter(
-                |c| matches!(c, Card::Regular(Rank::Three, suit) if suit.color() == Color::Red),
-            )
+            .drain_filter(|c| {
+                c.rank() == Some(Rank::Three) && c.suit().unwrap().color() == Color::Red
+            })
             .count();
 
         if red_threes_in_hand == 0 {
@@ -590,79 +835,502 @@ impl Game {
     }
 }
 
-fn main() {
-    let mut game = Game::deal(Round::One, 4);
-
-    for _ in 0..50 {
-        game.take_turn(
-            0,
-            |_, _player| DrawAction::Draw,
-            |round, player| {
-                let mut cards = player.play_area.clone();
-                for card in &player.hand {
-                    if let Some(rank) = card.rank() {
-                        if card.can_be_booked() {
-                            cards.entry(rank).or_insert_with(|| vec![]).push(*card);
-                        }
-                    }
-                }
+/// A read-only view of everything a player is legitimately allowed to see
+/// when making a decision: their own hand, books and play area in full (of
+/// their foot, only whether they still have one), and only the public
+/// parts of everyone else's (book counts and play areas, not hands/feet).
+#[derive(Clone, Debug)]
+struct PlayerView {
+    hand: Vec<Card>,
+    has_foot: bool,
+    books: Vec<Vec<Card>>,
+    red_threes: usize,
+    play_area: HashMap<Rank, Vec<Card>>,
+    opponents: Vec<OpponentView>,
+    discard_top: Option<Card>,
+    deck_size: usize,
+}
 
-                for wild in player.hand.iter().filter(|c| c.is_wild()) {
-                    if let Some(stack) = cards.values_mut().find(|stack| stack.len() == 2) {
-                        stack.push(*wild);
-                    }
-                }
+#[derive(Clone, Debug)]
+struct OpponentView {
+    book_count: usize,
+    play_area: HashMap<Rank, Vec<Card>>,
+}
 
-                let to_remove: Vec<_> = cards
-                    .iter()
-                    .filter(|(_, cs)| cs.len() < 3)
-                    .map(|(r, _)| *r)
-                    .collect();
-                for rank in to_remove {
-                    cards.remove(&rank);
-                }
+/// Rebuilds a [`PlayerView`] from a live [`PlayerCards`] mid-turn, keeping
+/// the opponent/discard/deck snapshot taken at the start of the turn, before
+/// the draw: `discard_top` and `deck_size` do not reflect the player's own
+/// draw or pickup.
+fn refresh_view(base: &PlayerView, player: &PlayerCards) -> PlayerView {
+    PlayerView {
+        hand: player.hand.clone(),
+        has_foot: player.foot.is_some(),
+        books: player.books.clone(),
+        red_threes: player.red_threes,
+        play_area: player.play_area.clone(),
+        opponents: base.opponents.clone(),
+        discard_top: base.discard_top,
+        deck_size: base.deck_size,
+    }
+}
 
-                for (rank, existing_cards) in &player.play_area {
-                    if let Some(to_add) = cards.get_mut(&rank) {
-                        for card in existing_cards {
-                            to_add.remove(to_add.iter().position(|x| x == card).unwrap());
-                        }
-                        while existing_cards.len() + to_add.len() > 7 {
-                            to_add.pop();
-                        }
-                    }
+/// The bot interface: a `Strategy` only ever gets to see a [`PlayerView`],
+/// so it can't cheat by inspecting other players' hands or feet.
+trait Strategy {
+    fn choose_draw(&self, round: Round, view: &PlayerView) -> DrawAction;
+    fn choose_plays(&self, round: Round, view: &PlayerView) -> HashMap<Rank, Vec<Card>>;
+    fn choose_discard(&self, round: Round, view: &PlayerView) -> Card;
+}
+
+/// The original throwaway bot from `main`, ported onto `Strategy`: always
+/// draws fresh, greedily books whatever it can, and discards its first card.
+struct GreedyBot;
+
+impl Strategy for GreedyBot {
+    fn choose_draw(&self, _round: Round, _view: &PlayerView) -> DrawAction {
+        DrawAction::Draw
+    }
+
+    fn choose_plays(&self, _round: Round, view: &PlayerView) -> HashMap<Rank, Vec<Card>> {
+        let mut cards = view.play_area.clone();
+        for card in &view.hand {
+            if let Some(rank) = card.rank() {
+                if card.can_be_booked() {
+                    cards.entry(rank).or_insert_with(|| vec![]).push(*card);
                 }
+            }
+        }
 
-                let num_attempting_to_play: usize = cards.values().map(Vec::len).sum();
-                if player.foot.is_none() {
-                    if num_attempting_to_play == player.hand.len() {
-                        dbg!("Could go out!");
-                    }
+        for wild in view.hand.iter().filter(|c| c.is_wild()) {
+            if let Some(stack) = cards.values_mut().find(|stack| stack.len() == 2) {
+                stack.push(*wild);
+            }
+        }
+
+        let to_remove: Vec<_> = cards
+            .iter()
+            .filter(|(_, cs)| cs.len() < 3)
+            .map(|(r, _)| *r)
+            .collect();
+        for rank in to_remove {
+            cards.remove(&rank);
+        }
+
+        for (rank, existing_cards) in &view.play_area {
+            if let Some(to_add) = cards.get_mut(rank) {
+                for card in existing_cards {
+                    to_add.remove(to_add.iter().position(|x| x == card).unwrap());
+                }
+                while existing_cards.len() + to_add.len() > 7 {
+                    to_add.pop();
                 }
+            }
+        }
+
+        cards
+    }
+
+    fn choose_discard(&self, _round: Round, view: &PlayerView) -> Card {
+        *view.hand.first().unwrap()
+    }
+}
+
+impl Game {
+    fn view(&self, player_idx: usize) -> PlayerView {
+        let player = &self.players[player_idx];
+        let opponents = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != player_idx)
+            .map(|(_, opponent)| OpponentView {
+                book_count: opponent.books.len(),
+                play_area: opponent.play_area.clone(),
+            })
+            .collect();
+
+        PlayerView {
+            hand: player.hand.clone(),
+            has_foot: player.foot.is_some(),
+            books: player.books.clone(),
+            red_threes: player.red_threes,
+            play_area: player.play_area.clone(),
+            opponents,
+            discard_top: self.discard_pile.0.last().copied(),
+            deck_size: self.deck.len(),
+        }
+    }
+}
 
-                if !cards.keys().is_empty() {
-                    let err = player.play(round, &cards);
-                    if err.is_err() {
-                        dbg!(err);
+/// Plays `game` turn by turn, round-robin starting at `start_player`, until
+/// a player goes `Out` or a turn errors out.
+fn play_round_robin(game: &mut Game, start_player: usize, strategies: &[Box<dyn Strategy>]) {
+    let num_players = strategies.len();
+
+    loop {
+        for offset in 0..num_players {
+            let player_idx = (start_player + offset) % num_players;
+            let base_view = game.view(player_idx);
+            let strategy = &strategies[player_idx];
+            let draw_action = strategy.choose_draw(game.round, &base_view);
+
+            let result = game.take_turn(
+                player_idx,
+                |_, _| draw_action,
+                |round, player| {
+                    let view = refresh_view(&base_view, player);
+                    let plays = strategy.choose_plays(round, &view);
+                    if !plays.is_empty() {
+                        let _ = player.play(round, &plays);
                     }
-                }
+                },
+                |round, player| {
+                    let view = refresh_view(&base_view, player);
+                    strategy.choose_discard(round, &view)
+                },
+            );
+
+            if !matches!(result, Ok(TurnResult::Over)) {
+                return;
+            }
+        }
+    }
+}
+
+/// Runs a complete match: all four `Round`s, dealing fresh each round and
+/// rotating the dealer, letting each `Strategy` play until someone goes
+/// `Out`, then accumulating `score()` across rounds.
+fn play_match(strategies: &[Box<dyn Strategy>]) -> Vec<isize> {
+    let num_players = strategies.len();
+    let mut total_scores = vec![0isize; num_players];
 
-                // println!(
-                //     "{}",
-                //     player
-                //         .hand
-                //         .iter()
-                //         .sorted()
-                //         .map(ToString::to_string)
-                //         .join(", ")
-                // );
-            },
-            |_, player| *player.hand.first().unwrap(),
-        )
-        .unwrap();
+    for (round_idx, round) in Round::iter().enumerate() {
+        let mut game = Game::deal(round, num_players);
+        let dealer = round_idx % num_players;
+
+        play_round_robin(&mut game, dealer, strategies);
+
+        for (total, delta) in total_scores.iter_mut().zip(game.score()) {
+            *total += delta;
+        }
+    }
+
+    total_scores
+}
+
+/// Reshuffles everything not already known to `player_idx` -- every other
+/// player's hand and foot, plus the undealt deck -- drawing replacements
+/// from `full_deck` (the true `num_players + 1`-deck composition) minus
+/// whatever is already visible: `player_idx`'s own hand/foot, every
+/// player's books/play area, the discard pile, and any already-banked red
+/// threes (those cards left every tracked collection the moment
+/// `resolve_red_threes` swapped them out, surviving only as a count, so
+/// they have to be subtracted by count rather than by value). Hand and
+/// foot sizes are preserved, so the resulting state still has exactly the
+/// multiset of cards the real game does.
+fn reshuffle_unseen(game: &mut Game, player_idx: usize, full_deck: &[Card], rng: &mut dyn RngCore) {
+    let mut unseen = full_deck.to_vec();
+    let mut remove_known = |cards: &[Card]| {
+        for card in cards {
+            if let Some(position) = unseen.iter().position(|c| c == card) {
+                unseen.remove(position);
+            }
+        }
+    };
+
+    for player in &game.players {
+        let played: Vec<Card> = player
+            .books
+            .iter()
+            .flatten()
+            .chain(player.play_area.values().flatten())
+            .copied()
+            .collect();
+        remove_known(&played);
     }
+    remove_known(&game.players[player_idx].hand);
+    if let Some(foot) = &game.players[player_idx].foot {
+        remove_known(foot);
+    }
+    remove_known(&game.discard_pile.0);
 
-    dbg!(game.score());
+    let mut banked_red_threes: usize = game.players.iter().map(|player| player.red_threes).sum();
+    unseen.retain(|card| {
+        if banked_red_threes > 0
+            && card.rank() == Some(Rank::Three)
+            && card.suit().is_some_and(|s| s.color() == Color::Red)
+        {
+            banked_red_threes -= 1;
+            false
+        } else {
+            true
+        }
+    });
 
-    println!("{}", game.players[0].hand[0]);
+    unseen.shuffle(rng);
+    let mut pool = unseen.into_iter();
+
+    for (idx, player) in game.players.iter_mut().enumerate() {
+        if idx == player_idx {
+            continue;
+        }
+        player.hand = pool.by_ref().take(player.hand.len()).collect();
+        if let Some(foot) = &mut player.foot {
+            *foot = pool.by_ref().take(foot.len()).collect();
+        }
+    }
+
+    game.deck = Deck(pool.collect());
+}
+
+impl Game {
+    /// Runs `samples` randomized playouts from this (possibly mid-turn)
+    /// state, each until a player goes out or a turn errors (as in
+    /// [`play_round_robin`], e.g. when the deck runs out), scores the
+    /// result and reports each player's share of wins, in the
+    /// style of the odds/chances tools poker players use. Everything not
+    /// visible to `player_idx` is reshuffled before each playout (see
+    /// [`reshuffle_unseen`]), and the rest of the game is then played out
+    /// with `GreedyBot` standing in for every player's unknown strategy.
+    /// Pass `seed` to make the estimate reproducible.
+    pub fn estimate_win_probability(
+        &self,
+        player_idx: usize,
+        samples: usize,
+        seed: Option<u64>,
+    ) -> Vec<f64> {
+        let num_players = self.players.len();
+        let mut rng: Box<dyn RngCore> = match seed {
+            Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+            None => Box::new(thread_rng()),
+        };
+
+        let full_deck: Vec<Card> = (0..(num_players + 1)).flat_map(|_| Card::iter()).collect();
+        let strategies: Vec<Box<dyn Strategy>> = (0..num_players)
+            .map(|_| Box::new(GreedyBot) as Box<dyn Strategy>)
+            .collect();
+
+        let mut wins = vec![0usize; num_players];
+        for _ in 0..samples {
+            let mut game = self.clone();
+            reshuffle_unseen(&mut game, player_idx, &full_deck, rng.as_mut());
+
+            play_round_robin(&mut game, player_idx, &strategies);
+
+            let scores = game.score();
+            let winner = scores
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, score)| **score)
+                .map(|(idx, _)| idx)
+                .unwrap();
+            wins[winner] += 1;
+        }
+
+        wins.iter()
+            .map(|&count| count as f64 / samples as f64)
+            .collect()
+    }
+}
+
+fn main() {
+    let strategies: Vec<Box<dyn Strategy>> =
+        (0..4).map(|_| Box::new(GreedyBot) as Box<dyn Strategy>).collect();
+
+    let scores = play_match(&strategies);
+
+    dbg!(scores);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_area_serializes_sorted_by_rank_regardless_of_insertion_order() {
+        let nine = Card::regular(Rank::Nine, Suit::Heart);
+        let king = Card::regular(Rank::King, Suit::Spade);
+
+        let mut inserted_king_first = HashMap::new();
+        inserted_king_first.insert(Rank::King, vec![king]);
+        inserted_king_first.insert(Rank::Nine, vec![nine]);
+
+        let mut inserted_nine_first = HashMap::new();
+        inserted_nine_first.insert(Rank::Nine, vec![nine]);
+        inserted_nine_first.insert(Rank::King, vec![king]);
+
+        let player_a = PlayerCards {
+            hand: vec![],
+            foot: None,
+            books: vec![],
+            red_threes: 0,
+            play_area: inserted_king_first,
+        };
+        let player_b = PlayerCards {
+            hand: vec![],
+            foot: None,
+            books: vec![],
+            red_threes: 0,
+            play_area: inserted_nine_first,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&player_a).unwrap(),
+            serde_json::to_string(&player_b).unwrap(),
+        );
+    }
+
+    #[test]
+    fn game_round_trips_through_json() {
+        let game = Game::deal_seeded(Round::One, 4, 42);
+
+        let json = game.to_json();
+        let restored = Game::from_json(&json).expect("round-tripped JSON should parse back");
+
+        assert_eq!(restored.to_json(), json);
+        assert_eq!(restored.score(), game.score());
+    }
+
+    #[test]
+    fn best_play_prefers_the_higher_value_wild_regardless_of_hand_order() {
+        let four_a = Card::regular(Rank::Four, Suit::Heart);
+        let four_b = Card::regular(Rank::Four, Suit::Diamond);
+        let two = Card::regular(Rank::Two, Suit::Club);
+        let joker = Card::joker();
+
+        for hand in [vec![four_a, four_b, two, joker], vec![four_a, four_b, joker, two]] {
+            let mut play_area = HashMap::new();
+            play_area.insert(
+                Rank::Nine,
+                vec![
+                    Card::regular(Rank::Nine, Suit::Heart),
+                    Card::regular(Rank::Nine, Suit::Diamond),
+                    Card::regular(Rank::Nine, Suit::Club),
+                ],
+            );
+
+            let player = PlayerCards {
+                hand,
+                foot: Some(vec![]),
+                books: vec![],
+                red_threes: 0,
+                play_area,
+            };
+
+            let planned = player.best_play(Round::One).expect("a legal play exists");
+            let fours = planned
+                .plays
+                .get(&Rank::Four)
+                .expect("the solver should play the two natural fours");
+
+            assert!(
+                fours.contains(&joker),
+                "solver should spend the 50-point joker, not the 20-point two, on the same book"
+            );
+            assert!(!fours.contains(&two));
+        }
+    }
+
+    #[test]
+    fn deal_seeded_is_deterministic_and_seed_dependent() {
+        let a = Game::deal_seeded(Round::One, 4, 7);
+        let b = Game::deal_seeded(Round::One, 4, 7);
+        assert_eq!(a.to_json(), b.to_json(), "same seed should deal the same game");
+
+        let c = Game::deal_seeded(Round::One, 4, 8);
+        assert_ne!(a.to_json(), c.to_json(), "different seeds should (almost always) differ");
+    }
+
+    #[test]
+    fn reshuffle_unseen_preserves_the_total_red_three_count() {
+        let is_red_three = |c: &Card| {
+            c.rank() == Some(Rank::Three) && c.suit().is_some_and(|s| s.color() == Color::Red)
+        };
+        let count_red_threes = |game: &Game| -> usize {
+            game.players
+                .iter()
+                .map(|player| {
+                    player.red_threes
+                        + player
+                            .hand
+                            .iter()
+                            .chain(player.foot.iter().flatten())
+                            .chain(player.books.iter().flatten())
+                            .chain(player.play_area.values().flatten())
+                            .filter(|c| is_red_three(c))
+                            .count()
+                })
+                .sum::<usize>()
+                + game.deck.0.iter().filter(|c| is_red_three(c)).count()
+                + game.discard_pile.0.iter().filter(|c| is_red_three(c)).count()
+        };
+
+        let mut game = Game::deal_seeded(Round::One, 4, 1);
+        let total_before = count_red_threes(&game);
+
+        game.resolve_red_threes(0).unwrap();
+
+        let full_deck: Vec<Card> = (0..(game.players.len() + 1)).flat_map(|_| Card::iter()).collect();
+        reshuffle_unseen(&mut game, 0, &full_deck, &mut StdRng::seed_from_u64(99));
+
+        assert_eq!(count_red_threes(&game), total_before, "reshuffling must not create phantom red threes");
+    }
+
+    #[test]
+    fn card_iter_yields_54_cards_with_two_jokers() {
+        let cards: Vec<Card> = Card::iter().collect();
+        assert_eq!(cards.len(), 54);
+        assert_eq!(cards.iter().filter(|c| c.rank().is_none()).count(), 2);
+    }
+
+    #[test]
+    fn card_round_trips_rank_and_suit_for_every_regular_card() {
+        for rank in Rank::iter() {
+            for suit in Suit::iter() {
+                let card = Card::regular(rank, suit);
+                assert_eq!(card.rank(), Some(rank));
+                assert_eq!(card.suit(), Some(suit));
+            }
+        }
+
+        let joker = Card::joker();
+        assert_eq!(joker.rank(), None);
+        assert_eq!(joker.suit(), None);
+    }
+
+    #[test]
+    fn card_points_is_wild_and_can_be_booked_match_the_rules_for_every_rank() {
+        for rank in Rank::iter() {
+            for suit in Suit::iter() {
+                let card = Card::regular(rank, suit);
+
+                let expected_points = match (rank, suit.color()) {
+                    (Rank::Three, Color::Red) => 100,
+                    (Rank::Ace, _) | (Rank::Two, _) => 20,
+                    (Rank::Three, _) => 0,
+                    (Rank::Four, _) | (Rank::Five, _) | (Rank::Six, _) | (Rank::Seven, _) => 5,
+                    _ => 10,
+                };
+                assert_eq!(card.points(), expected_points, "{:?} of {:?}", rank, suit);
+
+                assert_eq!(card.is_wild(), rank == Rank::Two);
+                assert_eq!(card.can_be_booked(), !matches!(rank, Rank::Two | Rank::Three));
+            }
+        }
+
+        let joker = Card::joker();
+        assert_eq!(joker.points(), 50);
+        assert!(joker.is_wild());
+        assert!(!joker.can_be_booked());
+    }
+
+    #[test]
+    fn play_match_terminates_with_one_score_per_player() {
+        let strategies: Vec<Box<dyn Strategy>> =
+            (0..4).map(|_| Box::new(GreedyBot) as Box<dyn Strategy>).collect();
+
+        let scores = play_match(&strategies);
+
+        assert_eq!(scores.len(), 4);
+    }
 }